@@ -16,7 +16,7 @@ extern crate embedded_hal;
 #[cfg(feature = "async")]
 extern crate embedded_hal_async;
 
-use embedded_hal::spi::{SpiDevice, Mode, Phase, Polarity};
+use embedded_hal::spi::{Mode, Phase, Polarity, SpiDevice};
 #[cfg(feature = "async")]
 use embedded_hal_async::spi::SpiDevice as AsyncSpiDevice;
 
@@ -26,128 +26,402 @@ pub const MODE: Mode = Mode {
     polarity: Polarity::IdleLow,
 };
 
+/// Reference voltage (in millivolts) assumed by [`Mcp3008::new`] / [`Mcp3004::new`]
+/// when no reference voltage is supplied explicitly.
+pub const DEFAULT_VREF_MV: u32 = 3300;
+
+/// A minimal SPI bus abstraction for the MCP3008/MCP3004.
+///
+/// This decouples the driver from [`embedded_hal::spi::SpiDevice`] so that bit-banged
+/// or PIO-based backends (e.g. the RP2040 PIO) which manage chip-select themselves can
+/// drive this ADC without implementing the full `SpiDevice` trait. A blanket impl is
+/// provided for every `SpiDevice`, so existing users are unaffected.
+pub trait Mcp3008Bus {
+    /// Error type returned by bus operations.
+    type Error;
+
+    /// Perform a 3-byte full-duplex exchange and return the 3 bytes shifted back
+    /// from the device. Implementors are responsible for asserting and releasing
+    /// chip-select around the exchange.
+    fn exchange(&mut self, write: [u8; 3]) -> Result<[u8; 3], Self::Error>;
+}
+
+impl<T> Mcp3008Bus for T
+where
+    T: SpiDevice,
+{
+    type Error = T::Error;
+
+    fn exchange(&mut self, write: [u8; 3]) -> Result<[u8; 3], Self::Error> {
+        let mut read = [0u8; 3];
+        self.transaction(&mut [embedded_hal::spi::Operation::Transfer(&mut read, &write)])?;
+        Ok(read)
+    }
+}
+
+/// A minimal async SPI bus abstraction for the MCP3008/MCP3004.
+///
+/// This trait is only available when the "async" feature is enabled. See
+/// [`Mcp3008Bus`] for the rationale; a blanket impl is provided for every
+/// `embedded_hal_async::spi::SpiDevice`.
+#[cfg(feature = "async")]
+pub trait AsyncMcp3008Bus {
+    /// Error type returned by bus operations.
+    type Error;
+
+    /// Perform a 3-byte full-duplex exchange and return the 3 bytes shifted back
+    /// from the device. Implementors are responsible for asserting and releasing
+    /// chip-select around the exchange.
+    ///
+    /// Spelled as `-> impl Future<...>` rather than `async fn` so the trait itself
+    /// doesn't trip `async_fn_in_trait` under this crate's `#![deny(warnings)]`.
+    fn exchange(
+        &mut self,
+        write: [u8; 3],
+    ) -> impl core::future::Future<Output = Result<[u8; 3], Self::Error>>;
+}
+
+#[cfg(feature = "async")]
+impl<T> AsyncMcp3008Bus for T
+where
+    T: AsyncSpiDevice,
+{
+    type Error = T::Error;
+
+    async fn exchange(&mut self, write: [u8; 3]) -> Result<[u8; 3], Self::Error> {
+        let mut read = [0u8; 3];
+        self.transaction(&mut [embedded_hal_async::spi::Operation::Transfer(
+            &mut read, &write,
+        )])
+        .await?;
+        Ok(read)
+    }
+}
+
 /// Synchronous MCP3008 driver
-pub struct Mcp3008<SpiDev> {
-    spi_dev: SpiDev,
+pub struct Mcp3008<Bus> {
+    bus: Bus,
+    vref_mv: u32,
 }
 
 #[cfg(feature = "async")]
 /// Asynchronous MCP3008 driver
 ///
 /// This struct is only available when the "async" feature is enabled.
-pub struct AsyncMcp3008<SpiDev> {
-    spi_dev: SpiDev,
+pub struct AsyncMcp3008<Bus> {
+    bus: Bus,
+    vref_mv: u32,
 }
 
 /// Synchronous MCP3004 driver
-pub struct Mcp3004<SpiDev> {
-    spi_dev: SpiDev,
+pub struct Mcp3004<Bus> {
+    bus: Bus,
+    vref_mv: u32,
 }
 
 #[cfg(feature = "async")]
 /// Asynchronous MCP3004 driver
 ///
 /// This struct is only available when the "async" feature is enabled.
-pub struct AsyncMcp3004<SpiDev> {
-    spi_dev: SpiDev,
+pub struct AsyncMcp3004<Bus> {
+    bus: Bus,
+    vref_mv: u32,
 }
 
-impl<SpiDev> Mcp3008<SpiDev>
+impl<Bus> Mcp3008<Bus>
 where
-    SpiDev: SpiDevice,
+    Bus: Mcp3008Bus,
 {
-    /// Creates a new driver from an SPI device.
-    pub fn new(spi_dev: SpiDev) -> Self {
-        Mcp3008 { spi_dev }
+    /// Creates a new driver from a bus implementing [`Mcp3008Bus`] (including any
+    /// `embedded_hal::spi::SpiDevice`).
+    pub fn new(bus: Bus) -> Self {
+        Mcp3008 {
+            bus,
+            vref_mv: DEFAULT_VREF_MV,
+        }
+    }
+
+    /// Creates a new driver from a bus, calibrated to the given reference voltage
+    /// (in millivolts) for use with [`Mcp3008::read_channel_mv`].
+    pub fn with_reference_mv(bus: Bus, vref_mv: u32) -> Self {
+        Mcp3008 { bus, vref_mv }
     }
 
     /// Read a MCP3008 ADC channel and return the 10 bit value as a u16
-    pub fn read_channel(&mut self, ch: Channels8) -> Result<u16, SpiDev::Error> {
+    pub fn read_channel(&mut self, ch: Channels8) -> Result<u16, Bus::Error> {
         let write_buffer = [1, ((1 << 3) | (ch as u8)) << 4, 0];
-        let mut read_buffer = [0u8; 3];
+        let read_buffer = self.bus.exchange(write_buffer)?;
 
-        self.spi_dev.transaction(&mut [
-            embedded_hal::spi::Operation::Write(&write_buffer),
-            embedded_hal::spi::Operation::Read(&mut read_buffer),
-        ])?;
+        let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
+        Ok(r)
+    }
+
+    /// Read a MCP3008 differential channel pair and return the 10 bit value as a u16
+    pub fn read_channel_differential(&mut self, ch: DiffChannels8) -> Result<u16, Bus::Error> {
+        let write_buffer = [1, (ch as u8) << 4, 0]; // SGL/DIFF = 0 selects a differential pair
+        let read_buffer = self.bus.exchange(write_buffer)?;
 
         let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
         Ok(r)
     }
+
+    /// Read a MCP3008 ADC channel and return it converted to millivolts, using the
+    /// reference voltage configured via [`Mcp3008::with_reference_mv`].
+    pub fn read_channel_mv(&mut self, ch: Channels8) -> Result<u32, Bus::Error> {
+        let r = self.read_channel(ch)?;
+        Ok((r as u32 * self.vref_mv) / 1023)
+    }
+
+    /// Read several MCP3008 channels in order, filling `out` with the 10 bit value
+    /// of each corresponding entry in `chans`. `chans` and `out` must have the same
+    /// length; any extra entries on the longer side are silently ignored.
+    pub fn read_channels(&mut self, chans: &[Channels8], out: &mut [u16]) -> Result<(), Bus::Error> {
+        for (ch, slot) in chans.iter().zip(out.iter_mut()) {
+            *slot = self.read_channel(*ch)?;
+        }
+        Ok(())
+    }
+
+    /// Read all 8 MCP3008 channels in order and return their 10 bit values.
+    pub fn read_all(&mut self) -> Result<[u16; 8], Bus::Error> {
+        let mut out = [0u16; 8];
+        self.read_channels(
+            &[
+                Channels8::CH0,
+                Channels8::CH1,
+                Channels8::CH2,
+                Channels8::CH3,
+                Channels8::CH4,
+                Channels8::CH5,
+                Channels8::CH6,
+                Channels8::CH7,
+            ],
+            &mut out,
+        )?;
+        Ok(out)
+    }
 }
 
 #[cfg(feature = "async")]
-impl<SpiDev> AsyncMcp3008<SpiDev>
+impl<Bus> AsyncMcp3008<Bus>
 where
-    SpiDev: AsyncSpiDevice,
+    Bus: AsyncMcp3008Bus,
 {
-    /// Creates a new async driver from an SPI device.
-    pub fn new(spi_dev: SpiDev) -> Self {
-        AsyncMcp3008 { spi_dev }
+    /// Creates a new async driver from a bus implementing [`AsyncMcp3008Bus`]
+    /// (including any `embedded_hal_async::spi::SpiDevice`).
+    pub fn new(bus: Bus) -> Self {
+        AsyncMcp3008 {
+            bus,
+            vref_mv: DEFAULT_VREF_MV,
+        }
+    }
+
+    /// Creates a new async driver from a bus, calibrated to the given reference
+    /// voltage (in millivolts) for use with [`AsyncMcp3008::read_channel_mv`].
+    pub fn with_reference_mv(bus: Bus, vref_mv: u32) -> Self {
+        AsyncMcp3008 { bus, vref_mv }
     }
 
     /// Read a MCP3008 ADC channel and return the 10 bit value as a u16
-    pub async fn read_channel(&mut self, ch: Channels8) -> Result<u16, SpiDev::Error> {
+    pub async fn read_channel(&mut self, ch: Channels8) -> Result<u16, Bus::Error> {
         let write_buffer = [1, ((1 << 3) | (ch as u8)) << 4, 0];
-        let mut read_buffer = [0u8; 3];
+        let read_buffer = self.bus.exchange(write_buffer).await?;
 
-        self.spi_dev.transaction(&mut [
-            embedded_hal_async::spi::Operation::Write(&write_buffer),
-            embedded_hal_async::spi::Operation::Read(&mut read_buffer),
-        ]).await?;
+        let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
+        Ok(r)
+    }
+
+    /// Read a MCP3008 differential channel pair and return the 10 bit value as a u16
+    pub async fn read_channel_differential(
+        &mut self,
+        ch: DiffChannels8,
+    ) -> Result<u16, Bus::Error> {
+        let write_buffer = [1, (ch as u8) << 4, 0]; // SGL/DIFF = 0 selects a differential pair
+        let read_buffer = self.bus.exchange(write_buffer).await?;
 
         let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
         Ok(r)
     }
+
+    /// Read a MCP3008 ADC channel and return it converted to millivolts, using the
+    /// reference voltage configured via [`AsyncMcp3008::with_reference_mv`].
+    pub async fn read_channel_mv(&mut self, ch: Channels8) -> Result<u32, Bus::Error> {
+        let r = self.read_channel(ch).await?;
+        Ok((r as u32 * self.vref_mv) / 1023)
+    }
+
+    /// Read several MCP3008 channels in order, filling `out` with the 10 bit value
+    /// of each corresponding entry in `chans`. `chans` and `out` must have the same
+    /// length; any extra entries on the longer side are silently ignored.
+    pub async fn read_channels(
+        &mut self,
+        chans: &[Channels8],
+        out: &mut [u16],
+    ) -> Result<(), Bus::Error> {
+        for (ch, slot) in chans.iter().zip(out.iter_mut()) {
+            *slot = self.read_channel(*ch).await?;
+        }
+        Ok(())
+    }
+
+    /// Read all 8 MCP3008 channels in order and return their 10 bit values.
+    pub async fn read_all(&mut self) -> Result<[u16; 8], Bus::Error> {
+        let mut out = [0u16; 8];
+        self.read_channels(
+            &[
+                Channels8::CH0,
+                Channels8::CH1,
+                Channels8::CH2,
+                Channels8::CH3,
+                Channels8::CH4,
+                Channels8::CH5,
+                Channels8::CH6,
+                Channels8::CH7,
+            ],
+            &mut out,
+        )
+        .await?;
+        Ok(out)
+    }
 }
 
-impl<SpiDev> Mcp3004<SpiDev>
+impl<Bus> Mcp3004<Bus>
 where
-    SpiDev: SpiDevice,
+    Bus: Mcp3008Bus,
 {
-    /// Creates a new driver from an SPI device.
-    pub fn new(spi_dev: SpiDev) -> Self {
-        Mcp3004 { spi_dev }
+    /// Creates a new driver from a bus implementing [`Mcp3008Bus`] (including any
+    /// `embedded_hal::spi::SpiDevice`).
+    pub fn new(bus: Bus) -> Self {
+        Mcp3004 {
+            bus,
+            vref_mv: DEFAULT_VREF_MV,
+        }
+    }
+
+    /// Creates a new driver from a bus, calibrated to the given reference voltage
+    /// (in millivolts) for use with [`Mcp3004::read_channel_mv`].
+    pub fn with_reference_mv(bus: Bus, vref_mv: u32) -> Self {
+        Mcp3004 { bus, vref_mv }
     }
 
     /// Read a MCP3004 ADC channel and return the 10 bit value as a u16
-    pub fn read_channel(&mut self, ch: Channels4) -> Result<u16, SpiDev::Error> {
+    pub fn read_channel(&mut self, ch: Channels4) -> Result<u16, Bus::Error> {
         let write_buffer = [1, ((1 << 3) | (ch as u8)) << 4, 0];
-        let mut read_buffer = [0u8; 3];
+        let read_buffer = self.bus.exchange(write_buffer)?;
+
+        let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
+        Ok(r)
+    }
 
-        self.spi_dev.transaction(&mut [
-            embedded_hal::spi::Operation::Write(&write_buffer),
-            embedded_hal::spi::Operation::Read(&mut read_buffer),
-        ])?;
+    /// Read a MCP3004 differential channel pair and return the 10 bit value as a u16
+    pub fn read_channel_differential(&mut self, ch: DiffChannels4) -> Result<u16, Bus::Error> {
+        let write_buffer = [1, (ch as u8) << 4, 0]; // SGL/DIFF = 0 selects a differential pair
+        let read_buffer = self.bus.exchange(write_buffer)?;
 
         let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
         Ok(r)
     }
+
+    /// Read a MCP3004 ADC channel and return it converted to millivolts, using the
+    /// reference voltage configured via [`Mcp3004::with_reference_mv`].
+    pub fn read_channel_mv(&mut self, ch: Channels4) -> Result<u32, Bus::Error> {
+        let r = self.read_channel(ch)?;
+        Ok((r as u32 * self.vref_mv) / 1023)
+    }
+
+    /// Read several MCP3004 channels in order, filling `out` with the 10 bit value
+    /// of each corresponding entry in `chans`. `chans` and `out` must have the same
+    /// length; any extra entries on the longer side are silently ignored.
+    pub fn read_channels(&mut self, chans: &[Channels4], out: &mut [u16]) -> Result<(), Bus::Error> {
+        for (ch, slot) in chans.iter().zip(out.iter_mut()) {
+            *slot = self.read_channel(*ch)?;
+        }
+        Ok(())
+    }
+
+    /// Read all 4 MCP3004 channels in order and return their 10 bit values.
+    pub fn read_all(&mut self) -> Result<[u16; 4], Bus::Error> {
+        let mut out = [0u16; 4];
+        self.read_channels(
+            &[Channels4::CH0, Channels4::CH1, Channels4::CH2, Channels4::CH3],
+            &mut out,
+        )?;
+        Ok(out)
+    }
 }
 
 #[cfg(feature = "async")]
-impl<SpiDev> AsyncMcp3004<SpiDev>
+impl<Bus> AsyncMcp3004<Bus>
 where
-    SpiDev: AsyncSpiDevice,
+    Bus: AsyncMcp3008Bus,
 {
-    /// Creates a new async driver from an SPI device.
-    pub fn new(spi_dev: SpiDev) -> Self {
-        AsyncMcp3004 { spi_dev }
+    /// Creates a new async driver from a bus implementing [`AsyncMcp3008Bus`]
+    /// (including any `embedded_hal_async::spi::SpiDevice`).
+    pub fn new(bus: Bus) -> Self {
+        AsyncMcp3004 {
+            bus,
+            vref_mv: DEFAULT_VREF_MV,
+        }
+    }
+
+    /// Creates a new async driver from a bus, calibrated to the given reference
+    /// voltage (in millivolts) for use with [`AsyncMcp3004::read_channel_mv`].
+    pub fn with_reference_mv(bus: Bus, vref_mv: u32) -> Self {
+        AsyncMcp3004 { bus, vref_mv }
     }
 
     /// Read a MCP3004 ADC channel and return the 10 bit value as a u16
-    pub async fn read_channel(&mut self, ch: Channels4) -> Result<u16, SpiDev::Error> {
+    pub async fn read_channel(&mut self, ch: Channels4) -> Result<u16, Bus::Error> {
         let write_buffer = [1, ((1 << 3) | (ch as u8)) << 4, 0];
-        let mut read_buffer = [0u8; 3];
+        let read_buffer = self.bus.exchange(write_buffer).await?;
+
+        let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
+        Ok(r)
+    }
 
-        self.spi_dev.transaction(&mut [
-            embedded_hal_async::spi::Operation::Write(&write_buffer),
-            embedded_hal_async::spi::Operation::Read(&mut read_buffer),
-        ]).await?;
+    /// Read a MCP3004 differential channel pair and return the 10 bit value as a u16
+    pub async fn read_channel_differential(
+        &mut self,
+        ch: DiffChannels4,
+    ) -> Result<u16, Bus::Error> {
+        let write_buffer = [1, (ch as u8) << 4, 0]; // SGL/DIFF = 0 selects a differential pair
+        let read_buffer = self.bus.exchange(write_buffer).await?;
 
         let r = (((read_buffer[1] as u16) << 8) | (read_buffer[2] as u16)) & 0x3ff;
         Ok(r)
     }
+
+    /// Read a MCP3004 ADC channel and return it converted to millivolts, using the
+    /// reference voltage configured via [`AsyncMcp3004::with_reference_mv`].
+    pub async fn read_channel_mv(&mut self, ch: Channels4) -> Result<u32, Bus::Error> {
+        let r = self.read_channel(ch).await?;
+        Ok((r as u32 * self.vref_mv) / 1023)
+    }
+
+    /// Read several MCP3004 channels in order, filling `out` with the 10 bit value
+    /// of each corresponding entry in `chans`. `chans` and `out` must have the same
+    /// length; any extra entries on the longer side are silently ignored.
+    pub async fn read_channels(
+        &mut self,
+        chans: &[Channels4],
+        out: &mut [u16],
+    ) -> Result<(), Bus::Error> {
+        for (ch, slot) in chans.iter().zip(out.iter_mut()) {
+            *slot = self.read_channel(*ch).await?;
+        }
+        Ok(())
+    }
+
+    /// Read all 4 MCP3004 channels in order and return their 10 bit values.
+    pub async fn read_all(&mut self) -> Result<[u16; 4], Bus::Error> {
+        let mut out = [0u16; 4];
+        self.read_channels(
+            &[Channels4::CH0, Channels4::CH1, Channels4::CH2, Channels4::CH3],
+            &mut out,
+        )
+        .await?;
+        Ok(out)
+    }
 }
 
 /// Channel list for MCP3008
@@ -173,3 +447,27 @@ pub enum Channels4 {
     CH2,
     CH3,
 }
+
+/// Differential channel pairs for MCP3008
+#[derive(Clone, Copy)]
+#[allow(missing_docs)]
+pub enum DiffChannels8 {
+    CH0Pos1Neg = 0b000,
+    CH1Pos0Neg = 0b001,
+    CH2Pos3Neg = 0b010,
+    CH3Pos2Neg = 0b011,
+    CH4Pos5Neg = 0b100,
+    CH5Pos4Neg = 0b101,
+    CH6Pos7Neg = 0b110,
+    CH7Pos6Neg = 0b111,
+}
+
+/// Differential channel pairs for MCP3004
+#[derive(Clone, Copy)]
+#[allow(missing_docs)]
+pub enum DiffChannels4 {
+    CH0Pos1Neg = 0b000,
+    CH1Pos0Neg = 0b001,
+    CH2Pos3Neg = 0b010,
+    CH3Pos2Neg = 0b011,
+}